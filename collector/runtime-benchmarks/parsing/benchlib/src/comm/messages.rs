@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+/// A single benchmark run's measurements, as reported by `measure::perf_counter`.
+/// Each counter is `None` when the CPU/kernel didn't support it or it never got scheduled.
+/// Counts from a `multiplexed` run are scaled-up estimates rather than raw counts.
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkStats {
+    pub cycles: Option<u64>,
+    pub instructions: Option<u64>,
+    pub branch_misses: Option<u64>,
+    pub cache_misses: Option<u64>,
+    pub cache_references: Option<u64>,
+    pub context_switches: Option<u64>,
+    pub cpu_migrations: Option<u64>,
+    pub page_faults: Option<u64>,
+    pub l1d_read_access: Option<u64>,
+    pub l1d_read_miss: Option<u64>,
+    pub ll_read_access: Option<u64>,
+    pub ll_read_miss: Option<u64>,
+    pub task_clock: Option<u64>,
+    pub wall_time: Duration,
+    /// Whether any counter in this run had to be time-sliced against other counters because the
+    /// group held more events than the CPU has physical counters for.
+    pub multiplexed: bool,
+}
@@ -1,8 +1,17 @@
 use crate::benchmark::black_box;
 use crate::comm::messages::BenchmarkStats;
-use perf_event::events::Hardware;
-use perf_event::{Builder, Counter, Group};
-use std::time::Instant;
+use perf_event::events::{Cache, CacheOp, CacheResult, Hardware, Software, WhichCache};
+use perf_event::{Builder, Counter, Counts, Group};
+use std::time::{Duration, Instant};
+
+/// What a `Group`'s counters should observe.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// Observe the calling thread, as `benchmark_function` does.
+    CurrentThread,
+    /// Observe a separately spawned/signalled process, as `benchmark_pid` does.
+    Pid(u32),
+}
 
 /// A collection of CPU performance counters.
 /// The counters are optional, because some CPUs are not able to record them.
@@ -12,6 +21,14 @@ struct Counters {
     branch_misses: Option<Counter>,
     cache_misses: Option<Counter>,
     cache_references: Option<Counter>,
+    context_switches: Option<Counter>,
+    cpu_migrations: Option<Counter>,
+    page_faults: Option<Counter>,
+    l1d_read_access: Option<Counter>,
+    l1d_read_miss: Option<Counter>,
+    ll_read_access: Option<Counter>,
+    ll_read_miss: Option<Counter>,
+    task_clock: Option<Counter>,
 }
 
 /// Benchmarks a single function generated by `benchmark_constructor`.
@@ -19,9 +36,10 @@ struct Counters {
 /// gather perf. counters.
 pub fn benchmark_function<F: Fn() -> Bench, R, Bench: FnOnce() -> R>(
     benchmark_constructor: &F,
+    detailed_cache_counters: bool,
 ) -> anyhow::Result<BenchmarkStats> {
-    let mut group = create_group()?;
-    let counters = prepare_counters(&mut group)?;
+    let mut group = create_group(Target::CurrentThread)?;
+    let counters = prepare_counters(&mut group, detailed_cache_counters, Target::CurrentThread)?;
 
     // Measure perf. counters.
     let func = benchmark_constructor();
@@ -49,37 +67,297 @@ pub fn benchmark_function<F: Fn() -> Bench, R, Bench: FnOnce() -> R>(
     // Try to avoid optimizing the result out.
     black_box(output);
 
+    Ok(build_stats(counters, measurement, duration))
+}
+
+/// Benchmarks a separately spawned/signalled process identified by `pid`, rather than a closure
+/// running in the harness's own thread. `run_workload` hands control to that process.
+pub fn benchmark_pid<F: FnOnce() -> R, R>(
+    pid: u32,
+    detailed_cache_counters: bool,
+    run_workload: F,
+) -> anyhow::Result<BenchmarkStats> {
+    let target = Target::Pid(pid);
+    let mut group = create_group(target)?;
+    let counters = prepare_counters(&mut group, detailed_cache_counters, target)?;
+
+    let enable_ret = group.enable();
+    let start = Instant::now();
+    let output = run_workload();
+    let duration = start.elapsed();
+    group.disable()?;
+
+    black_box(output);
+
+    enable_ret?;
+
+    let measurement = group.read()?;
+
+    Ok(build_stats(counters, measurement, duration))
+}
+
+/// Extracts a `BenchmarkStats` out of a `Counters`/`Counts` pair, scaling each raw count for
+/// multiplexing along the way.
+fn build_stats(counters: Counters, measurement: Counts, wall_time: Duration) -> BenchmarkStats {
+    // If more events were added to the group than there are physical counters, the kernel
+    // time-slices them and the raw counts only cover `time_running` out of `time_enabled`.
+    // Scale them back up to an estimate of what they would have been had they run the whole time.
+    let time_enabled = measurement.time_enabled();
+    let time_running = measurement.time_running();
+    let multiplexed = time_running != time_enabled;
+    let scale = |raw: u64| -> Option<u64> { scale_multiplexed(raw, time_enabled, time_running) };
+
     let result = BenchmarkStats {
-        cycles: counters.cycles.map(|c| measurement[&c]),
-        instructions: counters.instructions.map(|c| measurement[&c]),
-        branch_misses: counters.branch_misses.map(|c| measurement[&c]),
-        cache_misses: counters.cache_misses.map(|c| measurement[&c]),
-        cache_references: counters.cache_references.map(|c| measurement[&c]),
-        wall_time: duration,
+        cycles: counters.cycles.map(|c| measurement[&c]).and_then(scale),
+        instructions: counters
+            .instructions
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        branch_misses: counters
+            .branch_misses
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        cache_misses: counters
+            .cache_misses
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        cache_references: counters
+            .cache_references
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        context_switches: counters
+            .context_switches
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        cpu_migrations: counters
+            .cpu_migrations
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        page_faults: counters
+            .page_faults
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        l1d_read_access: counters
+            .l1d_read_access
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        l1d_read_miss: counters
+            .l1d_read_miss
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        ll_read_access: counters
+            .ll_read_access
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        ll_read_miss: counters
+            .ll_read_miss
+            .map(|c| measurement[&c])
+            .and_then(scale),
+        task_clock: counters.task_clock.map(|c| measurement[&c]).and_then(scale),
+        multiplexed,
+        wall_time,
     };
-    Ok(result)
+
+    // task_clock is captured inside the same enabled/disabled window as the other counters, so it
+    // gives a perf-derived busy time that can cross-check the separately measured wall time. If
+    // they diverge wildly, the thread was probably preempted for a large chunk of the run.
+    if let Some(task_clock_ns) = result.task_clock {
+        let wall_time_ns = result.wall_time.as_nanos() as u64;
+        if wall_time_ns > 0 && task_clock_ns < wall_time_ns / 2 {
+            log::warn!(
+                "task_clock ({} ns) is much smaller than wall_time ({} ns); \
+                 the benchmark may have been heavily preempted",
+                task_clock_ns,
+                wall_time_ns
+            );
+        }
+    }
+
+    result
+}
+
+/// Scales `raw` from covering `time_running` out of `time_enabled` up to an estimate of what it
+/// would have been had the counter run for the whole `time_enabled` window. `None` if the counter
+/// never got scheduled at all.
+fn scale_multiplexed(raw: u64, time_enabled: u64, time_running: u64) -> Option<u64> {
+    if time_running == 0 {
+        return None;
+    }
+    if time_enabled == time_running {
+        return Some(raw);
+    }
+    Some((raw as f64 * (time_enabled as f64 / time_running as f64)).round() as u64)
+}
+
+/// A min/median/mean/stddev summary of a metric gathered over several samples.
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    pub min: u64,
+    pub median: u64,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+fn summarize(mut values: Vec<u64>) -> Summary {
+    assert!(!values.is_empty());
+    values.sort_unstable();
+
+    let min = values[0];
+    let median = values[values.len() / 2];
+    let mean = values.iter().sum::<u64>() as f64 / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|&value| {
+            let diff = value as f64 - mean;
+            diff * diff
+        })
+        .sum::<f64>() /
+        values.len() as f64;
+
+    Summary {
+        min,
+        median,
+        mean,
+        stddev: variance.sqrt(),
+    }
+}
+
+/// Summarizes a metric across `samples`, skipping samples where the counter wasn't available.
+/// Returns `None` if no sample recorded the counter.
+fn summarize_metric(
+    samples: &[BenchmarkStats],
+    extract: impl Fn(&BenchmarkStats) -> Option<u64>,
+) -> Option<Summary> {
+    let values: Vec<u64> = samples.iter().filter_map(extract).collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(summarize(values))
+    }
+}
+
+/// A statistical summary of `BenchmarkStats` gathered over several iterations of the same
+/// benchmark.
+pub struct BenchmarkSamples {
+    pub cycles: Option<Summary>,
+    pub instructions: Option<Summary>,
+    pub branch_misses: Option<Summary>,
+    pub cache_misses: Option<Summary>,
+    pub cache_references: Option<Summary>,
+    pub context_switches: Option<Summary>,
+    pub cpu_migrations: Option<Summary>,
+    pub page_faults: Option<Summary>,
+    pub l1d_read_access: Option<Summary>,
+    pub l1d_read_miss: Option<Summary>,
+    pub ll_read_access: Option<Summary>,
+    pub ll_read_miss: Option<Summary>,
+    pub task_clock: Option<Summary>,
+    pub wall_time: Summary,
+    /// Whether any sample reported that its counters had to be multiplexed.
+    pub multiplexed: bool,
+}
+
+/// Benchmarks `benchmark_constructor` over `iterations` samples and returns a statistical
+/// summary. The first iteration is discarded as a warm-up.
+pub fn benchmark_function_sampled<F: Fn() -> Bench, R, Bench: FnOnce() -> R>(
+    benchmark_constructor: &F,
+    detailed_cache_counters: bool,
+    iterations: usize,
+) -> anyhow::Result<BenchmarkSamples> {
+    anyhow::ensure!(
+        iterations > 0,
+        "benchmark_function_sampled requires at least 1 iteration, got 0"
+    );
+
+    let _ = benchmark_function(benchmark_constructor, detailed_cache_counters)?;
+
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        samples.push(benchmark_function(
+            benchmark_constructor,
+            detailed_cache_counters,
+        )?);
+    }
+
+    let wall_time = summarize(
+        samples
+            .iter()
+            .map(|s| s.wall_time.as_nanos() as u64)
+            .collect(),
+    );
+    let multiplexed = samples.iter().any(|s| s.multiplexed);
+
+    Ok(BenchmarkSamples {
+        cycles: summarize_metric(&samples, |s| s.cycles),
+        instructions: summarize_metric(&samples, |s| s.instructions),
+        branch_misses: summarize_metric(&samples, |s| s.branch_misses),
+        cache_misses: summarize_metric(&samples, |s| s.cache_misses),
+        cache_references: summarize_metric(&samples, |s| s.cache_references),
+        context_switches: summarize_metric(&samples, |s| s.context_switches),
+        cpu_migrations: summarize_metric(&samples, |s| s.cpu_migrations),
+        page_faults: summarize_metric(&samples, |s| s.page_faults),
+        l1d_read_access: summarize_metric(&samples, |s| s.l1d_read_access),
+        l1d_read_miss: summarize_metric(&samples, |s| s.l1d_read_miss),
+        ll_read_access: summarize_metric(&samples, |s| s.ll_read_access),
+        ll_read_miss: summarize_metric(&samples, |s| s.ll_read_miss),
+        task_clock: summarize_metric(&samples, |s| s.task_clock),
+        wall_time,
+        multiplexed,
+    })
 }
 
-fn create_group() -> anyhow::Result<Group> {
-    match Group::new() {
+/// Reads the current `perf_event_paranoid` level, for inclusion in error messages.
+fn paranoid_level() -> String {
+    let path = "/proc/sys/kernel/perf_event_paranoid";
+    let level = std::fs::read_to_string(path).unwrap_or_else(|_| "unknown".to_string());
+    format!("Current value of {} is {}.", path, level.trim())
+}
+
+fn create_group(target: Target) -> anyhow::Result<Group> {
+    // `Group::new()` always builds its leader counter with the default target (the calling
+    // thread), which would make every sibling counter attached below it fail to open whenever
+    // `target` is actually `Pid`: `perf_event_open` requires every counter in a group to share
+    // the leader's exact target. Build the leader with `target` applied instead, so the whole
+    // group (leader included) observes the same thing `prepare_counters` attaches siblings to.
+    let mut builder = Builder::new();
+    if let Target::Pid(pid) = target {
+        builder = builder.observe_pid(pid as i32);
+    }
+    match builder.build_group() {
         Ok(group) => Ok(group),
-        Err(error) => {
-            let path = "/proc/sys/kernel/perf_event_paranoid";
-            let level = std::fs::read_to_string(path).unwrap_or_else(|_| "unknown".to_string());
-            let level = level.trim();
-            Err(anyhow::anyhow!(
-                "Cannot create perf_event group ({:?}). Current value of {} is {}.
+        Err(error) => match target {
+            Target::CurrentThread => Err(anyhow::anyhow!(
+                "Cannot create perf_event group ({:?}). {}
 Try lowering it with `sudo bash -c 'echo -1 > /proc/sys/kernel/perf_event_paranoid'`.",
                 error,
-                path,
-                level
-            ))
-        }
+                paranoid_level()
+            )),
+            Target::Pid(pid) => Err(anyhow::anyhow!(
+                "Cannot create perf_event group to observe pid {} ({:?}). {}
+Observing another process additionally requires CAP_PERFMON/CAP_SYS_ADMIN, or
+`perf_event_paranoid` <= 1. Try `sudo bash -c 'echo -1 > /proc/sys/kernel/perf_event_paranoid'`.",
+                pid,
+                error,
+                paranoid_level()
+            )),
+        },
     }
 }
 
-fn prepare_counters(group: &mut Group) -> anyhow::Result<Counters> {
-    let mut add_event = |event: Hardware| match Builder::new().group(group).kind(event).build() {
+fn prepare_counters(
+    group: &mut Group,
+    detailed_cache_counters: bool,
+    target: Target,
+) -> anyhow::Result<Counters> {
+    let mut builder_for = || {
+        let mut builder = Builder::new().group(group);
+        if let Target::Pid(pid) = target {
+            builder = builder.observe_pid(pid as i32);
+        }
+        builder
+    };
+
+    let mut add_event = |event: Hardware| match builder_for().kind(event).build() {
         Ok(counter) => Some(counter),
         Err(error) => {
             log::warn!(
@@ -92,16 +370,134 @@ fn prepare_counters(group: &mut Group) -> anyhow::Result<Counters> {
     };
 
     let cycles = add_event(Hardware::CPU_CYCLES);
+    // `CPU_CYCLES` is supported on effectively every CPU perf can run on, so if attaching it to
+    // a group whose leader already observes `target` (see `create_group`) still fails while
+    // observing another process, this is very likely a permission problem rather than an
+    // unsupported event, so surface it as a hard error instead of silently degrading.
+    if cycles.is_none() {
+        if let Target::Pid(pid) = target {
+            return Err(anyhow::anyhow!(
+                "Could not attach perf_event counters to pid {}. {}",
+                pid,
+                paranoid_level()
+            ));
+        }
+    }
     let instructions = add_event(Hardware::INSTRUCTIONS);
     let branch_misses = add_event(Hardware::BRANCH_MISSES);
     let cache_misses = add_event(Hardware::CACHE_MISSES);
     let cache_references = add_event(Hardware::CACHE_REFERENCES);
 
+    let mut add_software_event = |event: Software| match builder_for().kind(event).build() {
+        Ok(counter) => Some(counter),
+        Err(error) => {
+            log::warn!(
+                "Could not add counter {:?}: {:?}. Maybe the CPU doesn't support it?",
+                event,
+                error
+            );
+            None
+        },
+    };
+
+    let context_switches = add_software_event(Software::CONTEXT_SWITCHES);
+    let cpu_migrations = add_software_event(Software::CPU_MIGRATIONS);
+    let page_faults = add_software_event(Software::PAGE_FAULTS);
+    // Nanoseconds of on-CPU time, captured inside the same enabled/disabled window as the other
+    // counters. Lets callers compute CPU utilization (task_clock / wall_time) and cross-check it
+    // against the separately measured `Instant` wall time.
+    let task_clock = add_software_event(Software::TASK_CLOCK);
+
+    // Per-level cache counters are opt-in: they add four more events to the same `Group`,
+    // and a CPU only has a handful of physical counters to multiplex across.
+    let (l1d_read_access, l1d_read_miss, ll_read_access, ll_read_miss) = if detailed_cache_counters
+    {
+        let mut add_cache_event = |which: WhichCache, result: CacheResult| {
+            let event = Cache {
+                which,
+                operation: CacheOp::READ,
+                result,
+            };
+            match builder_for().kind(event).build() {
+                Ok(counter) => Some(counter),
+                Err(error) => {
+                    log::warn!(
+                        "Could not add cache counter {:?}: {:?}. Maybe the CPU doesn't support it?",
+                        event,
+                        error
+                    );
+                    None
+                },
+            }
+        };
+
+        (
+            add_cache_event(WhichCache::L1D, CacheResult::ACCESS),
+            add_cache_event(WhichCache::L1D, CacheResult::MISS),
+            add_cache_event(WhichCache::LL, CacheResult::ACCESS),
+            add_cache_event(WhichCache::LL, CacheResult::MISS),
+        )
+    } else {
+        (None, None, None, None)
+    };
+
     Ok(Counters {
         cycles,
         instructions,
         branch_misses,
         cache_misses,
         cache_references,
+        context_switches,
+        cpu_migrations,
+        page_faults,
+        l1d_read_access,
+        l1d_read_miss,
+        ll_read_access,
+        ll_read_miss,
+        task_clock,
     })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_multiplexed_never_scheduled() {
+        assert_eq!(scale_multiplexed(100, 1000, 0), None);
+    }
+
+    #[test]
+    fn scale_multiplexed_not_multiplexed() {
+        assert_eq!(scale_multiplexed(100, 1000, 1000), Some(100));
+    }
+
+    #[test]
+    fn scale_multiplexed_partially_scheduled() {
+        assert_eq!(scale_multiplexed(50, 1000, 500), Some(100));
+    }
+
+    #[test]
+    fn summarize_single_value() {
+        let summary = summarize(vec![42]);
+        assert_eq!(summary.min, 42);
+        assert_eq!(summary.median, 42);
+        assert_eq!(summary.mean, 42.0);
+        assert_eq!(summary.stddev, 0.0);
+    }
+
+    #[test]
+    fn summarize_multiple_values() {
+        let summary = summarize(vec![2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(summary.min, 2);
+        assert_eq!(summary.median, 5);
+        assert_eq!(summary.mean, 5.0);
+        assert_eq!(summary.stddev, 2.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn summarize_empty_panics() {
+        summarize(vec![]);
+    }
 }
\ No newline at end of file